@@ -0,0 +1,39 @@
+use tokio::sync::broadcast;
+use x25519_dalek::StaticSecret;
+
+use crate::config::Config;
+use crate::rate_limit::RateLimiter;
+use crate::route_handlers::Event;
+
+/// Bounded so the channel never grows without limit: once a receiver falls
+/// this far behind, the oldest events are overwritten and it sees a lag.
+const EVENT_BROADCAST_CAPACITY: usize = 1024;
+
+pub struct AppState {
+    pub config: Config,
+    pub rate_limiter: RateLimiter,
+    pub event_tx: broadcast::Sender<Event>,
+    pub server_x25519_secret: StaticSecret,
+}
+
+impl AppState {
+    pub fn new(config: Config) -> Self {
+        let server_x25519_secret = parse_x25519_secret(&config.server_x25519_secret_key);
+        let (event_tx, _) = broadcast::channel(EVENT_BROADCAST_CAPACITY);
+
+        Self {
+            config,
+            rate_limiter: RateLimiter::new(),
+            event_tx,
+            server_x25519_secret,
+        }
+    }
+}
+
+fn parse_x25519_secret(hex_key: &str) -> StaticSecret {
+    let bytes: [u8; 32] = hex::decode(hex_key)
+        .expect("SERVER_X25519_SECRET_KEY must be 32 bytes of hex")
+        .try_into()
+        .expect("SERVER_X25519_SECRET_KEY must be 32 bytes of hex");
+    StaticSecret::from(bytes)
+}
@@ -0,0 +1,41 @@
+use std::env;
+
+/// Server configuration, loaded from the environment at startup.
+#[derive(Clone)]
+pub struct Config {
+    pub secret_key: String,
+    pub create_session_cost: u64,
+    pub ingest_event_cost: u64,
+    /// Hex-encoded 32-byte X25519 static secret used to derive per-request
+    /// shared keys with clients' ephemeral public keys for authenticated ingest.
+    pub server_x25519_secret_key: String,
+    /// Cap on the `limit` a caller may request from `/events/query`.
+    pub max_query_limit: u32,
+    /// Require a per-session ingest token (in addition to `session_id`) at
+    /// `ingest_event`. Off by default so existing clients keep working.
+    pub enforce_ingest_tokens: bool,
+    /// Cap on the number of events accepted in a single `/events/batch` call.
+    pub max_batch_size: usize,
+}
+
+impl Config {
+    pub fn from_env() -> Self {
+        Self {
+            secret_key: env::var("SECRET_KEY").expect("SECRET_KEY must be set"),
+            create_session_cost: env_or("CREATE_SESSION_COST", 1),
+            ingest_event_cost: env_or("INGEST_EVENT_COST", 1),
+            server_x25519_secret_key: env::var("SERVER_X25519_SECRET_KEY")
+                .expect("SERVER_X25519_SECRET_KEY must be set"),
+            max_query_limit: env_or("MAX_QUERY_LIMIT", 1000),
+            enforce_ingest_tokens: env_or("ENFORCE_INGEST_TOKENS", false),
+            max_batch_size: env_or("MAX_BATCH_SIZE", 500),
+        }
+    }
+}
+
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
@@ -0,0 +1,134 @@
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes256Gcm, Nonce};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use x25519_dalek::{PublicKey as X25519PublicKey, StaticSecret};
+
+/// ECDH key agreement, AEAD, and signature verification primitives backing
+/// `route_handlers::ingest_event_secure`.
+#[derive(Debug)]
+pub enum CryptoError {
+    BadKey,
+    BadSignature,
+    Decrypt,
+}
+
+/// Derive the AES-256-GCM symmetric key from the server's static X25519
+/// secret and a client's ephemeral X25519 public key.
+pub fn get_x25519_symmetric_key(
+    server_secret: &StaticSecret,
+    client_ephemeral_pub: &[u8; 32],
+) -> [u8; 32] {
+    let client_pub = X25519PublicKey::from(*client_ephemeral_pub);
+    server_secret.diffie_hellman(&client_pub).to_bytes()
+}
+
+/// Not called by the server (clients encrypt, the server only decrypts via
+/// `decrypt_aes_gcm`); kept alongside it so the pair can be tested together
+/// and so a client implementation in this crate has somewhere to live.
+#[allow(dead_code)]
+pub fn encrypt_aes_gcm(key: &[u8; 32], iv: &[u8; 12], plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::BadKey)?;
+    cipher
+        .encrypt(Nonce::from_slice(iv), plaintext)
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+pub fn decrypt_aes_gcm(key: &[u8; 32], iv: &[u8; 12], ciphertext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+    let cipher = Aes256Gcm::new_from_slice(key).map_err(|_| CryptoError::BadKey)?;
+    cipher
+        .decrypt(Nonce::from_slice(iv), ciphertext)
+        .map_err(|_| CryptoError::Decrypt)
+}
+
+/// Verify an ed25519 signature over the canonical event bytes against a
+/// session's registered hex-encoded public key.
+pub fn verify_event_signature(
+    pubkey_hex: &str,
+    canonical_event: &[u8],
+    signature_hex: &str,
+) -> Result<(), CryptoError> {
+    let pubkey_bytes: [u8; 32] = hex::decode(pubkey_hex)
+        .map_err(|_| CryptoError::BadKey)?
+        .try_into()
+        .map_err(|_| CryptoError::BadKey)?;
+    let verifying_key = VerifyingKey::from_bytes(&pubkey_bytes).map_err(|_| CryptoError::BadKey)?;
+
+    let sig_bytes: [u8; 64] = hex::decode(signature_hex)
+        .map_err(|_| CryptoError::BadSignature)?
+        .try_into()
+        .map_err(|_| CryptoError::BadSignature)?;
+    let signature = Signature::from_bytes(&sig_bytes);
+
+    verifying_key
+        .verify(canonical_event, &signature)
+        .map_err(|_| CryptoError::BadSignature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn aes_gcm_round_trips_plaintext() {
+        let key = [7u8; 32];
+        let iv = [1u8; 12];
+        let plaintext = b"hello ingest";
+
+        let ciphertext = encrypt_aes_gcm(&key, &iv, plaintext).unwrap();
+        let decrypted = decrypt_aes_gcm(&key, &iv, &ciphertext).unwrap();
+
+        assert_eq!(decrypted, plaintext);
+    }
+
+    #[test]
+    fn aes_gcm_decrypt_fails_with_wrong_key() {
+        let iv = [1u8; 12];
+        let ciphertext = encrypt_aes_gcm(&[7u8; 32], &iv, b"hello ingest").unwrap();
+
+        let result = decrypt_aes_gcm(&[9u8; 32], &iv, &ciphertext);
+
+        assert!(matches!(result, Err(CryptoError::Decrypt)));
+    }
+
+    #[test]
+    fn x25519_ecdh_is_symmetric() {
+        let server_secret = StaticSecret::from([1u8; 32]);
+        let client_secret = StaticSecret::from([2u8; 32]);
+        let client_public = X25519PublicKey::from(&client_secret).to_bytes();
+        let server_public = X25519PublicKey::from(&server_secret).to_bytes();
+
+        let server_side = get_x25519_symmetric_key(&server_secret, &client_public);
+        let client_side = get_x25519_symmetric_key(&client_secret, &server_public);
+
+        assert_eq!(server_side, client_side);
+    }
+
+    #[test]
+    fn verify_event_signature_accepts_a_valid_signature() {
+        let signing_key = SigningKey::from_bytes(&[3u8; 32]);
+        let pubkey_hex = hex::encode(signing_key.verifying_key().to_bytes());
+        let message = b"{\"event_name\":\"level_up\"}";
+        let signature_hex = hex::encode(signing_key.sign(message).to_bytes());
+
+        assert!(verify_event_signature(&pubkey_hex, message, &signature_hex).is_ok());
+    }
+
+    #[test]
+    fn verify_event_signature_rejects_a_tampered_message() {
+        let signing_key = SigningKey::from_bytes(&[3u8; 32]);
+        let pubkey_hex = hex::encode(signing_key.verifying_key().to_bytes());
+        let signature_hex = hex::encode(signing_key.sign(b"original").to_bytes());
+
+        let result = verify_event_signature(&pubkey_hex, b"tampered", &signature_hex);
+
+        assert!(matches!(result, Err(CryptoError::BadSignature)));
+    }
+
+    #[test]
+    fn verify_event_signature_rejects_a_malformed_pubkey() {
+        let result = verify_event_signature("not-hex", b"message", "00");
+
+        assert!(matches!(result, Err(CryptoError::BadKey)));
+    }
+}
@@ -0,0 +1,316 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+static DB_PATH: OnceLock<PathBuf> = OnceLock::new();
+static CONNECTION: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS sessions (
+        session_id TEXT PRIMARY KEY,
+        start_date INTEGER NOT NULL,
+        ip_address TEXT NOT NULL,
+        pub_key TEXT,
+        ingest_token_hash TEXT
+    );
+    CREATE TABLE IF NOT EXISTS events (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        session_id TEXT NOT NULL,
+        event_name TEXT NOT NULL,
+        time INTEGER NOT NULL,
+        ip_address TEXT NOT NULL,
+        params TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_events_session_event_time
+        ON events (session_id, event_name, time);
+";
+
+/// Open (creating if needed) the SQLite store at `path` and apply the schema.
+/// Must be called once at startup before `with_connection` is used.
+pub fn init(path: &str) {
+    let conn = open_and_migrate(path);
+    DB_PATH.set(PathBuf::from(path)).ok();
+    CONNECTION.set(Mutex::new(conn)).ok();
+}
+
+fn open_and_migrate(path: &str) -> Connection {
+    let conn = Connection::open(path).expect("failed to open sqlite database");
+    conn.execute_batch(SCHEMA).expect("failed to apply schema");
+    conn
+}
+
+pub fn with_connection<F, R>(f: F) -> R
+where
+    F: FnOnce(&mut Connection) -> R,
+{
+    let mut conn = CONNECTION
+        .get()
+        .expect("db_pool::init must run before with_connection")
+        .lock()
+        .unwrap();
+    f(&mut conn)
+}
+
+#[derive(Serialize)]
+pub struct IntegrityReport {
+    pub was_healthy: bool,
+    pub recovered_rows: u64,
+    pub dropped_rows: u64,
+    pub detail: String,
+}
+
+/// Run `PRAGMA integrity_check` (and a WAL checkpoint) against the live
+/// database. If it reports corruption, salvage every readable `sessions`/
+/// `events` row into a fresh database file via streaming queries that skip
+/// rows which fail to decode, then atomically swap the fresh file in,
+/// keeping the damaged file as a `.corrupt` backup. Safe to call repeatedly;
+/// a healthy database is a no-op beyond the checkpoint.
+///
+/// Callable as a manual admin action while the server is serving traffic
+/// (see `route_handlers::run_integrity_check`), so the pool's lock is held
+/// for the entire check-and-recover sequence, not just each individual
+/// query: otherwise a concurrent `ingest_event`/`ingest_events_batch` call
+/// could insert a row the salvage SELECT never saw (silently dropping it
+/// when the rebuilt file is swapped in) or write to the file out from under
+/// the salvage entirely.
+pub fn check_and_recover_integrity() -> IntegrityReport {
+    let path = DB_PATH
+        .get()
+        .expect("db_pool::init must run before check_and_recover_integrity")
+        .clone();
+
+    let mut conn = CONNECTION
+        .get()
+        .expect("db_pool::init must run before check_and_recover_integrity")
+        .lock()
+        .unwrap();
+
+    let _: String = conn
+        .pragma_query_value(None, "wal_checkpoint(TRUNCATE)", |row| row.get(0))
+        .unwrap_or_default();
+
+    let integrity_result: String = conn
+        .pragma_query_value(None, "integrity_check", |row| row.get(0))
+        .unwrap_or_else(|_| "error".to_string());
+
+    if integrity_result == "ok" {
+        return IntegrityReport {
+            was_healthy: true,
+            recovered_rows: 0,
+            dropped_rows: 0,
+            detail: "integrity_check reported ok".to_string(),
+        };
+    }
+
+    let (recovered_rows, dropped_rows) = recover(&path, &mut conn);
+
+    IntegrityReport {
+        was_healthy: false,
+        recovered_rows,
+        dropped_rows,
+        detail: format!("integrity_check reported: {integrity_result}"),
+    }
+}
+
+/// Copy every `sessions` row that decodes cleanly from `damaged` into
+/// `rebuilt`. Returns `(recovered, dropped)`. A row whose columns fail to
+/// decode (the SQLite-level equivalent of a half-written record) is counted
+/// as dropped instead of aborting the whole salvage.
+fn salvage_sessions(damaged: &Connection, rebuilt: &Connection) -> (u64, u64) {
+    let mut recovered = 0u64;
+    let mut dropped = 0u64;
+
+    let mut stmt = damaged
+        .prepare("SELECT session_id, start_date, ip_address, pub_key, ingest_token_hash FROM sessions")
+        .expect("sessions table missing from damaged database");
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, String>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+            ))
+        })
+        .expect("failed to stream sessions for salvage");
+
+    for row in rows {
+        match row {
+            Ok((session_id, start_date, ip_address, pub_key, ingest_token_hash)) => {
+                let inserted = rebuilt.execute(
+                    "INSERT OR IGNORE INTO sessions (session_id, start_date, ip_address, pub_key, ingest_token_hash) \
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    rusqlite::params![session_id, start_date, ip_address, pub_key, ingest_token_hash],
+                );
+                match inserted {
+                    Ok(_) => recovered += 1,
+                    Err(_) => dropped += 1,
+                }
+            }
+            Err(_) => dropped += 1,
+        }
+    }
+
+    (recovered, dropped)
+}
+
+/// Copy every `events` row that decodes cleanly from `damaged` into
+/// `rebuilt`. Returns `(recovered, dropped)`, same semantics as
+/// `salvage_sessions`.
+fn salvage_events(damaged: &Connection, rebuilt: &Connection) -> (u64, u64) {
+    let mut recovered = 0u64;
+    let mut dropped = 0u64;
+
+    let mut stmt = damaged
+        .prepare("SELECT session_id, event_name, time, ip_address, params FROM events")
+        .expect("events table missing from damaged database");
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i64>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+            ))
+        })
+        .expect("failed to stream events for salvage");
+
+    for row in rows {
+        match row {
+            Ok((session_id, event_name, time, ip_address, params)) => {
+                let inserted = rebuilt.execute(
+                    "INSERT INTO events (session_id, event_name, time, ip_address, params) VALUES (?1, ?2, ?3, ?4, json(?5))",
+                    rusqlite::params![session_id, event_name, time, ip_address, params],
+                );
+                match inserted {
+                    Ok(_) => recovered += 1,
+                    Err(_) => dropped += 1,
+                }
+            }
+            Err(_) => dropped += 1,
+        }
+    }
+
+    (recovered, dropped)
+}
+
+/// Salvage whatever is readable out of the corrupted file at `path` into a
+/// freshly created database, then atomically rename it over the original and
+/// point `live` (the pool's already-locked connection) at the swapped-in
+/// file. Callers must hold the pool's lock across this whole call — it
+/// mutates `live` directly rather than going back through
+/// `with_connection`, which would deadlock on the lock the caller holds.
+/// The damaged file is kept alongside as `<path>.corrupt` rather than deleted.
+fn recover(path: &Path, live: &mut Connection) -> (u64, u64) {
+    let damaged = Connection::open(path).expect("failed to reopen damaged database for salvage");
+
+    let rebuilt_path = path.with_extension("rebuilt");
+    let _ = fs::remove_file(&rebuilt_path);
+    let rebuilt = open_and_migrate(rebuilt_path.to_str().unwrap());
+
+    let (sessions_recovered, sessions_dropped) = salvage_sessions(&damaged, &rebuilt);
+    let (events_recovered, events_dropped) = salvage_events(&damaged, &rebuilt);
+    let recovered_rows = sessions_recovered + events_recovered;
+    let dropped_rows = sessions_dropped + events_dropped;
+
+    drop(rebuilt);
+    drop(damaged);
+
+    let mut corrupt_backup = path.as_os_str().to_owned();
+    corrupt_backup.push(".corrupt");
+    fs::copy(path, &corrupt_backup).expect("failed to back up damaged database");
+    fs::rename(&rebuilt_path, path).expect("failed to swap in recovered database");
+
+    *live = open_and_migrate(path.to_str().unwrap());
+
+    (recovered_rows, dropped_rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn open_test_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(SCHEMA).unwrap();
+        conn
+    }
+
+    #[test]
+    fn salvage_sessions_copies_well_formed_rows() {
+        let damaged = open_test_db();
+        damaged
+            .execute(
+                "INSERT INTO sessions (session_id, start_date, ip_address, pub_key, ingest_token_hash) \
+                 VALUES ('s1', 1000, '127.0.0.1', 'deadbeef', 'abc123')",
+                [],
+            )
+            .unwrap();
+
+        let rebuilt = open_test_db();
+        let (recovered, dropped) = salvage_sessions(&damaged, &rebuilt);
+
+        assert_eq!((recovered, dropped), (1, 0));
+        let count: i64 = rebuilt
+            .query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn salvage_sessions_drops_rows_that_fail_to_decode() {
+        let damaged = open_test_db();
+        // A non-numeric start_date can't be read back as the `i64` the schema expects,
+        // which is the SQLite-level stand-in for a half-written record.
+        damaged
+            .execute(
+                "INSERT INTO sessions (session_id, start_date, ip_address) VALUES ('s1', 'not-a-number', '127.0.0.1')",
+                [],
+            )
+            .unwrap();
+        damaged
+            .execute(
+                "INSERT INTO sessions (session_id, start_date, ip_address) VALUES ('s2', 2000, '127.0.0.1')",
+                [],
+            )
+            .unwrap();
+
+        let rebuilt = open_test_db();
+        let (recovered, dropped) = salvage_sessions(&damaged, &rebuilt);
+
+        assert_eq!((recovered, dropped), (1, 1));
+    }
+
+    #[test]
+    fn salvage_events_copies_well_formed_rows_and_drops_bad_ones() {
+        let damaged = open_test_db();
+        damaged
+            .execute(
+                "INSERT INTO events (session_id, event_name, time, ip_address, params) \
+                 VALUES ('s1', 'level_up', 1000, '127.0.0.1', '{}')",
+                [],
+            )
+            .unwrap();
+        damaged
+            .execute(
+                "INSERT INTO events (session_id, event_name, time, ip_address, params) \
+                 VALUES ('s1', 'level_up', 'not-a-number', '127.0.0.1', '{}')",
+                [],
+            )
+            .unwrap();
+
+        let rebuilt = open_test_db();
+        let (recovered, dropped) = salvage_events(&damaged, &rebuilt);
+
+        assert_eq!((recovered, dropped), (1, 1));
+        let count: i64 = rebuilt
+            .query_row("SELECT COUNT(*) FROM events", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(count, 1);
+    }
+}
@@ -0,0 +1,49 @@
+use actix_web::{web, App, HttpServer};
+
+mod app_state;
+mod config;
+mod crypto;
+mod db_pool;
+mod rate_limit;
+mod route_handlers;
+mod ws;
+
+use app_state::AppState;
+use config::Config;
+
+#[actix_web::main]
+async fn main() -> std::io::Result<()> {
+    let config = Config::from_env();
+    let db_path = std::env::var("DB_PATH").unwrap_or_else(|_| "events.db".to_string());
+    db_pool::init(&db_path);
+
+    let report = db_pool::check_and_recover_integrity();
+    if !report.was_healthy {
+        eprintln!(
+            "warning: recovered from a corrupted database: {} rows recovered, {} dropped ({})",
+            report.recovered_rows, report.dropped_rows, report.detail
+        );
+    }
+
+    let data = web::Data::new(AppState::new(config));
+
+    HttpServer::new(move || {
+        App::new()
+            .app_data(data.clone())
+            .route("/sessions", web::post().to(route_handlers::create_session))
+            .route("/sessions", web::get().to(route_handlers::get_sessions))
+            .route("/events", web::post().to(route_handlers::ingest_event))
+            .route("/events/secure", web::post().to(route_handlers::ingest_event_secure))
+            .route("/events/batch", web::post().to(route_handlers::ingest_events_batch))
+            .route("/events/query", web::post().to(route_handlers::query_events))
+            .route("/events/{session_id}", web::get().to(route_handlers::get_events))
+            .route("/subscribe", web::get().to(ws::subscribe))
+            .route(
+                "/admin/integrity-check",
+                web::post().to(route_handlers::run_integrity_check),
+            )
+    })
+    .bind(("0.0.0.0", 8080))?
+    .run()
+    .await
+}
@@ -0,0 +1,56 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::app_state::AppState;
+
+const WINDOW: Duration = Duration::from_secs(60);
+const CAPACITY: u64 = 600;
+
+struct Bucket {
+    tokens: u64,
+    window_start: Instant,
+}
+
+/// Per-IP token bucket, refilled to `CAPACITY` every `WINDOW`.
+pub struct RateLimiter {
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self {
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Default for RateLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Charge `cost` tokens against `key`'s bucket, refilling it first if the
+/// window has elapsed. Returns `false` (and charges nothing) if the bucket
+/// doesn't have enough tokens left.
+pub fn check_rate_limit(data: &AppState, key: &str, cost: u64) -> bool {
+    let mut buckets = data.rate_limiter.buckets.lock().unwrap();
+    let now = Instant::now();
+    let bucket = buckets.entry(key.to_string()).or_insert_with(|| Bucket {
+        tokens: CAPACITY,
+        window_start: now,
+    });
+
+    if now.duration_since(bucket.window_start) >= WINDOW {
+        bucket.tokens = CAPACITY;
+        bucket.window_start = now;
+    }
+
+    if bucket.tokens < cost {
+        return false;
+    }
+
+    bucket.tokens -= cost;
+    true
+}
@@ -1,14 +1,30 @@
 use actix_web::{web, Error, HttpRequest, HttpResponse, Responder};
+use base64::Engine;
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
 use rusqlite::params;
 use serde_json::Value;
+use sha3::{Digest, Sha3_256};
+use subtle::ConstantTimeEq;
 use uuid::Uuid;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::crypto;
 use crate::db_pool;
 use crate::app_state::{AppState};
 use crate::rate_limit::{check_rate_limit};
 
+/// Check the shared `X-Secret-Key` header used by the read/admin endpoints,
+/// in constant time. A missing or non-UTF-8 header is treated as "no match"
+/// rather than panicking.
+pub(crate) fn check_secret_key(req: &HttpRequest, expected: &str) -> bool {
+    req.headers()
+        .get("X-Secret-Key")
+        .and_then(|value| value.to_str().ok())
+        .map(|presented| presented.as_bytes().ct_eq(expected.as_bytes()).into())
+        .unwrap_or(false)
+}
+
 #[derive(Deserialize)]
 pub struct IngestEventRequest {
     session_id: String,
@@ -17,17 +33,42 @@ pub struct IngestEventRequest {
     params: Value,
 }
 
+/// Wrapper for the authenticated-ingest mode: `ciphertext` decrypts (via
+/// X25519 ECDH + AES-256-GCM) to the canonical JSON bytes of an
+/// `IngestEventRequest`, and `signature` is the client's ed25519 signature
+/// over those same plaintext bytes.
+#[derive(Deserialize)]
+pub struct EncryptedIngestEventRequest {
+    session_id: String,
+    client_ephemeral_pub_key: String,
+    iv: String,
+    ciphertext: String,
+    signature: String,
+}
+
+#[derive(Deserialize, Default)]
+pub struct CreateSessionRequest {
+    /// Hex-encoded ed25519 public key the client registers for this session.
+    /// Required to use the authenticated-ingest endpoint.
+    #[serde(default)]
+    pub_key: Option<String>,
+}
+
 #[derive(Serialize)]
 struct CreateSessionResponse {
-    session_id: String
+    session_id: String,
+    /// Plaintext per-session ingest token, returned once. Only its SHA3-256
+    /// hash is persisted, so a caller that loses this cannot recover it.
+    ingest_token: String,
 }
 
-#[derive(Serialize)]
-struct Event {
-    id: i64,
-    event_name: String,
-    time: u64,
-    params: Value,
+#[derive(Serialize, Clone)]
+pub(crate) struct Event {
+    pub(crate) id: i64,
+    pub(crate) session_id: String,
+    pub(crate) event_name: String,
+    pub(crate) time: u64,
+    pub(crate) params: Value,
 }
 
 #[derive(Serialize)]
@@ -36,7 +77,11 @@ struct SessionInfo {
     start_date: u64,
 }
 
-pub async fn create_session(req: HttpRequest, data: web::Data<AppState>) -> impl Responder {
+pub async fn create_session(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    payload: Option<web::Json<CreateSessionRequest>>,
+) -> impl Responder {
     // Rate limiting per IP address
     let ip = req
         .peer_addr()
@@ -52,20 +97,68 @@ pub async fn create_session(req: HttpRequest, data: web::Data<AppState>) -> impl
         .duration_since(UNIX_EPOCH)
         .unwrap()
         .as_secs();
+    let pub_key = payload.and_then(|body| body.into_inner().pub_key);
+    let ingest_token = generate_ingest_token();
+    let ingest_token_hash = hash_ingest_token(&ingest_token);
 
     db_pool::with_connection(|conn| {
         conn.execute(
-            "INSERT INTO sessions (session_id, start_date, ip_address) VALUES (?1, ?2, ?3)",
-            params![session_id, start_date, ip],
+            "INSERT INTO sessions (session_id, start_date, ip_address, pub_key, ingest_token_hash) \
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![session_id, start_date, ip, pub_key, ingest_token_hash],
         )
         .unwrap();
     });
 
     HttpResponse::Ok().json(CreateSessionResponse {
         session_id,
+        ingest_token,
     })
 }
 
+/// Generate a 32-byte, URL-safe base64 per-session ingest token.
+fn generate_ingest_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn hash_ingest_token(token: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update(token.as_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Require the `X-Ingest-Token` header to match the session's registered
+/// token (compared as hashes, in constant time) before an event is inserted.
+/// Gated behind `config.enforce_ingest_tokens` so existing clients that only
+/// know the `session_id` keep working until they're upgraded.
+fn check_ingest_token(req: &HttpRequest, session_id: &str) -> Result<(), HttpResponse> {
+    let presented = req
+        .headers()
+        .get("X-Ingest-Token")
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| HttpResponse::Unauthorized().body("Missing ingest token"))?;
+
+    let stored_hash: Option<String> = db_pool::with_connection(|conn| {
+        conn.query_row(
+            "SELECT ingest_token_hash FROM sessions WHERE session_id = ?1",
+            params![session_id],
+            |row| row.get(0),
+        )
+        .ok()
+    });
+
+    let stored_hash = stored_hash.ok_or_else(|| HttpResponse::Unauthorized().body("Unknown session"))?;
+    let presented_hash = hash_ingest_token(presented);
+
+    if presented_hash.as_bytes().ct_eq(stored_hash.as_bytes()).into() {
+        Ok(())
+    } else {
+        Err(HttpResponse::Unauthorized().body("Invalid ingest token"))
+    }
+}
+
 pub async fn ingest_event(
     req: HttpRequest,
     data: web::Data<AppState>,
@@ -81,7 +174,13 @@ pub async fn ingest_event(
         return HttpResponse::TooManyRequests().body("Rate limit exceeded");
     }
 
-    db_pool::with_connection(|conn| {
+    if data.config.enforce_ingest_tokens {
+        if let Err(response) = check_ingest_token(&req, &payload.session_id) {
+            return response;
+        }
+    }
+
+    let id = db_pool::with_connection(|conn| {
         conn.execute(
             "INSERT INTO events (session_id, event_name, time, ip_address, params) VALUES (?1, ?2, ?3, ?4, json(?5))",
             params![
@@ -93,19 +192,243 @@ pub async fn ingest_event(
             ],
         )
         .unwrap();
+
+        conn.last_insert_rowid()
+    });
+
+    // Fan the freshly inserted row out to any live `/subscribe` sockets. A lagging or
+    // absent receiver is not an error here, so ignore the send result.
+    let _ = data.event_tx.send(Event {
+        id,
+        session_id: payload.session_id.clone(),
+        event_name: payload.event_name.clone(),
+        time: payload.time,
+        params: payload.params.clone(),
+    });
+
+    HttpResponse::Ok().body("Event ingested")
+}
+
+/// Authenticated, confidential counterpart to `ingest_event`. The client
+/// encrypts its `IngestEventRequest` JSON under a key derived from ECDH
+/// between the server's static X25519 key and the client's ephemeral one,
+/// and signs the plaintext with the ed25519 key it registered at
+/// `create_session`. A decrypt or signature failure is rejected with 401
+/// rather than inserted.
+pub async fn ingest_event_secure(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    payload: web::Json<EncryptedIngestEventRequest>,
+) -> impl Responder {
+    let ip = req
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    if !check_rate_limit(&data, &ip, data.config.ingest_event_cost) {
+        return HttpResponse::TooManyRequests().body("Rate limit exceeded");
+    }
+
+    let session_pub_key = db_pool::with_connection(|conn| {
+        conn.query_row(
+            "SELECT pub_key FROM sessions WHERE session_id = ?1",
+            params![payload.session_id],
+            |row| row.get::<_, Option<String>>(0),
+        )
+    });
+    let session_pub_key = match session_pub_key {
+        Ok(Some(key)) => key,
+        _ => return HttpResponse::Unauthorized().body("Unknown session or no registered key"),
+    };
+
+    let client_ephemeral_pub_key: Option<[u8; 32]> = hex::decode(&payload.client_ephemeral_pub_key)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok());
+    let iv: Option<[u8; 12]> = hex::decode(&payload.iv).ok().and_then(|bytes| bytes.try_into().ok());
+    let (client_ephemeral_pub_key, iv) = match (client_ephemeral_pub_key, iv) {
+        (Some(pub_key), Some(iv)) => (pub_key, iv),
+        _ => return HttpResponse::Unauthorized().body("Malformed key material"),
+    };
+    let ciphertext = match hex::decode(&payload.ciphertext) {
+        Ok(bytes) => bytes,
+        Err(_) => return HttpResponse::Unauthorized().body("Malformed ciphertext"),
+    };
+
+    let symmetric_key =
+        crypto::get_x25519_symmetric_key(&data.server_x25519_secret, &client_ephemeral_pub_key);
+    let plaintext = match crypto::decrypt_aes_gcm(&symmetric_key, &iv, &ciphertext) {
+        Ok(bytes) => bytes,
+        Err(_) => return HttpResponse::Unauthorized().body("Decryption failed"),
+    };
+
+    if crypto::verify_event_signature(&session_pub_key, &plaintext, &payload.signature).is_err() {
+        return HttpResponse::Unauthorized().body("Invalid signature");
+    }
+
+    let event: IngestEventRequest = match serde_json::from_slice(&plaintext) {
+        Ok(event) => event,
+        Err(_) => return HttpResponse::Unauthorized().body("Malformed event payload"),
+    };
+    if event.session_id != payload.session_id {
+        return HttpResponse::Unauthorized().body("Session mismatch");
+    }
+
+    let id = db_pool::with_connection(|conn| {
+        conn.execute(
+            "INSERT INTO events (session_id, event_name, time, ip_address, params) VALUES (?1, ?2, ?3, ?4, json(?5))",
+            params![
+                event.session_id,
+                event.event_name,
+                event.time,
+                ip,
+                event.params.to_string()
+            ],
+        )
+        .unwrap();
+
+        conn.last_insert_rowid()
+    });
+
+    let _ = data.event_tx.send(Event {
+        id,
+        session_id: event.session_id.clone(),
+        event_name: event.event_name.clone(),
+        time: event.time,
+        params: event.params.clone(),
     });
 
     HttpResponse::Ok().body("Event ingested")
 }
 
+#[derive(Deserialize)]
+struct BatchEventItem {
+    event_name: String,
+    time: u64,
+    params: Value,
+}
+
+#[derive(Deserialize)]
+pub struct IngestEventsBatchRequest {
+    session_id: String,
+    events: Vec<BatchEventItem>,
+}
+
+#[derive(Serialize)]
+struct BatchRejection {
+    index: usize,
+    reason: String,
+}
+
+#[derive(Serialize)]
+struct IngestEventsBatchResponse {
+    accepted: usize,
+    rejected: Vec<BatchRejection>,
+}
+
+/// `POST /events/batch` — insert a whole burst of events in one transaction
+/// instead of one HTTP round-trip and one `conn.execute` per event. Rate
+/// limit is charged proportional to batch size, and a per-event rejection
+/// (rather than failing the whole batch) is reported for any row SQLite
+/// refuses.
+pub async fn ingest_events_batch(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    payload: web::Json<IngestEventsBatchRequest>,
+) -> impl Responder {
+    let ip = req
+        .peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    if payload.events.len() > data.config.max_batch_size {
+        return HttpResponse::PayloadTooLarge().body(format!(
+            "batch of {} events exceeds max_batch_size of {}",
+            payload.events.len(),
+            data.config.max_batch_size
+        ));
+    }
+
+    let cost = data.config.ingest_event_cost * payload.events.len() as u64;
+    if !check_rate_limit(&data, &ip, cost) {
+        return HttpResponse::TooManyRequests().body("Rate limit exceeded");
+    }
+
+    if data.config.enforce_ingest_tokens {
+        if let Err(response) = check_ingest_token(&req, &payload.session_id) {
+            return response;
+        }
+    }
+
+    let session_id = payload.session_id.clone();
+    let (inserted, rejected) = db_pool::with_connection(|conn| {
+        let tx = conn.transaction().unwrap();
+        let result = insert_batch(&tx, &session_id, &ip, &payload.events);
+        tx.commit().unwrap();
+        result
+    });
+
+    for event in &inserted {
+        let _ = data.event_tx.send(event.clone());
+    }
+
+    HttpResponse::Ok().json(IngestEventsBatchResponse {
+        accepted: inserted.len(),
+        rejected,
+    })
+}
+
+/// Insert each of `events` within `tx`, one row at a time. A row SQLite
+/// rejects is recorded by its index into `events` rather than aborting the
+/// transaction or the rest of the batch.
+fn insert_batch(
+    tx: &rusqlite::Transaction,
+    session_id: &str,
+    ip: &str,
+    events: &[BatchEventItem],
+) -> (Vec<Event>, Vec<BatchRejection>) {
+    let mut inserted = Vec::new();
+    let mut rejected = Vec::new();
+
+    let mut stmt = tx
+        .prepare_cached(
+            "INSERT INTO events (session_id, event_name, time, ip_address, params) VALUES (?1, ?2, ?3, ?4, json(?5))",
+        )
+        .unwrap();
+
+    for (index, event) in events.iter().enumerate() {
+        let result = stmt.execute(params![
+            session_id,
+            event.event_name,
+            event.time,
+            ip,
+            event.params.to_string()
+        ]);
+
+        match result {
+            Ok(_) => inserted.push(Event {
+                id: tx.last_insert_rowid(),
+                session_id: session_id.to_string(),
+                event_name: event.event_name.clone(),
+                time: event.time,
+                params: event.params.clone(),
+            }),
+            Err(e) => rejected.push(BatchRejection {
+                index,
+                reason: e.to_string(),
+            }),
+        }
+    }
+
+    (inserted, rejected)
+}
+
 pub async fn get_events(
     req: HttpRequest,
     data: web::Data<AppState>,
     path: web::Path<String>,
 ) -> Result<HttpResponse, Error> {
     // Check for shared secret
-    let secret = req.headers().get("X-Secret-Key");
-    if secret.is_none() || secret.unwrap().to_str().unwrap() != data.config.secret_key {
+    if !check_secret_key(&req, &data.config.secret_key) {
         return Ok(HttpResponse::Unauthorized().body("Invalid secret key"));
     }
 
@@ -114,17 +437,198 @@ pub async fn get_events(
     let events = db_pool::with_connection(|conn| {
         let mut stmt = conn
             .prepare_cached(
-                "SELECT id, event_name, time, params FROM events WHERE session_id = ?1 ORDER BY time ASC",
+                "SELECT id, session_id, event_name, time, params FROM events WHERE session_id = ?1 ORDER BY time ASC",
             )
             .unwrap();
 
         let events_iter = stmt
             .query_map(params![session_id], |row| {
-                let params_str: String = row.get(3)?;
+                let params_str: String = row.get(4)?;
                 Ok(Event {
                     id: row.get(0)?,
-                    event_name: row.get(1)?,
-                    time: row.get(2)?,
+                    session_id: row.get(1)?,
+                    event_name: row.get(2)?,
+                    time: row.get(3)?,
+                    params: serde_json::from_str(&params_str).unwrap_or(Value::Null),
+                })
+            })
+            .unwrap();
+
+        events_iter.map(|event| event.unwrap()).collect::<Vec<Event>>()
+    });
+
+    Ok(HttpResponse::Ok().json(events))
+}
+
+/// `event_name` may be a single name or a set of names to match any of.
+/// Shared with `ws::SubscribeFrame` so `/subscribe` can filter by the same
+/// shape as `/events/query`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub(crate) enum EventNameFilter {
+    One(String),
+    AnyOf(Vec<String>),
+}
+
+impl EventNameFilter {
+    /// Whether `name` matches this filter. Used by `ws::EventSubscription`
+    /// to test a freshly broadcast event in memory, outside of a SQL query.
+    pub(crate) fn matches(&self, name: &str) -> bool {
+        match self {
+            EventNameFilter::One(expected) => expected == name,
+            EventNameFilter::AnyOf(names) => names.iter().any(|n| n == name),
+        }
+    }
+}
+
+/// A predicate on a `params` field: either an implicit equality (`"biome": "caves"`)
+/// or an explicit comparator map (`"level": {"gte": 5}`). Multiple comparators on
+/// the same key are ANDed together.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ParamPredicate {
+    Cmp(std::collections::HashMap<String, Value>),
+    Eq(Value),
+}
+
+#[derive(Deserialize)]
+pub struct EventQueryRequest {
+    session_id: String,
+    #[serde(default)]
+    event_name: Option<EventNameFilter>,
+    #[serde(default)]
+    since: Option<u64>,
+    #[serde(default)]
+    until: Option<u64>,
+    #[serde(default)]
+    limit: Option<u32>,
+    #[serde(default)]
+    params: std::collections::HashMap<String, ParamPredicate>,
+}
+
+const COMPARATORS: &[(&str, &str)] = &[
+    ("eq", "="),
+    ("neq", "!="),
+    ("gt", ">"),
+    ("gte", ">="),
+    ("lt", "<"),
+    ("lte", "<="),
+];
+
+/// Append a `json_extract(params, '$.key') <op> ?` clause, binding the predicate
+/// value by its actual JSON type so e.g. a hex-looking string isn't coerced to a
+/// number (or vice versa).
+fn push_json_predicate(
+    clauses: &mut Vec<String>,
+    bound: &mut Vec<Box<dyn rusqlite::ToSql>>,
+    path: &str,
+    op: &str,
+    value: &Value,
+) -> Result<(), HttpResponse> {
+    match value {
+        Value::Number(n) => {
+            clauses.push(format!("CAST(json_extract(params, '{path}') AS REAL) {op} ?"));
+            bound.push(Box::new(n.as_f64().ok_or_else(|| {
+                HttpResponse::BadRequest().body(format!("unsupported number for {path}"))
+            })?));
+        }
+        Value::String(s) => {
+            clauses.push(format!("json_extract(params, '{path}') {op} ?"));
+            bound.push(Box::new(s.clone()));
+        }
+        Value::Bool(b) => {
+            clauses.push(format!("json_extract(params, '{path}') {op} ?"));
+            bound.push(Box::new(*b as i64));
+        }
+        _ => return Err(HttpResponse::BadRequest().body(format!("unsupported predicate value for {path}"))),
+    }
+    Ok(())
+}
+
+/// `POST /events/query` — a filtering layer over `get_events` for slicing data
+/// without pulling every row for a session. Supports `event_name`, a `since`/
+/// `until` time range, a result `limit`, and predicates on the JSON `params`
+/// payload translated into `json_extract` comparisons (e.g. `(session_id,
+/// event_name, time)` is indexed to keep these queries fast).
+pub async fn query_events(
+    req: HttpRequest,
+    data: web::Data<AppState>,
+    payload: web::Json<EventQueryRequest>,
+) -> Result<HttpResponse, Error> {
+    // Check for shared secret
+    if !check_secret_key(&req, &data.config.secret_key) {
+        return Ok(HttpResponse::Unauthorized().body("Invalid secret key"));
+    }
+
+    let mut clauses = vec!["session_id = ?".to_string()];
+    let mut bound: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(payload.session_id.clone())];
+
+    match &payload.event_name {
+        Some(EventNameFilter::One(name)) => {
+            clauses.push("event_name = ?".to_string());
+            bound.push(Box::new(name.clone()));
+        }
+        Some(EventNameFilter::AnyOf(names)) if !names.is_empty() => {
+            let placeholders = names.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+            clauses.push(format!("event_name IN ({placeholders})"));
+            for name in names {
+                bound.push(Box::new(name.clone()));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(since) = payload.since {
+        clauses.push("time >= ?".to_string());
+        bound.push(Box::new(since));
+    }
+    if let Some(until) = payload.until {
+        clauses.push("time <= ?".to_string());
+        bound.push(Box::new(until));
+    }
+
+    for (key, predicate) in &payload.params {
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Ok(HttpResponse::BadRequest().body(format!("invalid params key: {key}")));
+        }
+        let path = format!("$.{key}");
+
+        let result = match predicate {
+            ParamPredicate::Eq(value) => push_json_predicate(&mut clauses, &mut bound, &path, "=", value),
+            ParamPredicate::Cmp(ops) => ops.iter().try_for_each(|(op, value)| {
+                let sql_op = COMPARATORS
+                    .iter()
+                    .find(|(name, _)| *name == op)
+                    .map(|(_, sql)| *sql)
+                    .ok_or_else(|| HttpResponse::BadRequest().body(format!("unknown operator: {op}")))?;
+                push_json_predicate(&mut clauses, &mut bound, &path, sql_op, value)
+            }),
+        };
+        if let Err(response) = result {
+            return Ok(response);
+        }
+    }
+
+    let limit = payload.limit.unwrap_or(1000).min(data.config.max_query_limit);
+    bound.push(Box::new(limit));
+
+    let sql = format!(
+        "SELECT id, session_id, event_name, time, params FROM events WHERE {} ORDER BY time ASC LIMIT ?",
+        clauses.join(" AND "),
+    );
+
+    let events = db_pool::with_connection(|conn| {
+        let mut stmt = conn.prepare_cached(&sql).unwrap();
+        let bound_refs: Vec<&dyn rusqlite::ToSql> = bound.iter().map(|b| b.as_ref()).collect();
+
+        let events_iter = stmt
+            .query_map(bound_refs.as_slice(), |row| {
+                let params_str: String = row.get(4)?;
+                Ok(Event {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    event_name: row.get(2)?,
+                    time: row.get(3)?,
                     params: serde_json::from_str(&params_str).unwrap_or(Value::Null),
                 })
             })
@@ -138,8 +642,7 @@ pub async fn get_events(
 
 pub async fn get_sessions(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, Error> {
     // Check for shared secret
-    let secret = req.headers().get("X-Secret-Key");
-    if secret.is_none() || secret.unwrap().to_str().unwrap() != data.config.secret_key {
+    if !check_secret_key(&req, &data.config.secret_key) {
         return Ok(HttpResponse::Unauthorized().body("Invalid secret key"));
     }
 
@@ -164,3 +667,149 @@ pub async fn get_sessions(req: HttpRequest, data: web::Data<AppState>) -> Result
 
     Ok(HttpResponse::Ok().json(sessions))
 }
+
+#[derive(Serialize)]
+struct IntegrityCheckResponse {
+    was_healthy: bool,
+    recovered_rows: u64,
+    dropped_rows: u64,
+    detail: String,
+}
+
+/// Admin counterpart to the automatic startup integrity check: runs `PRAGMA
+/// integrity_check` (and a `wal_checkpoint(TRUNCATE)`) against the live
+/// database and, if corruption is found, salvages readable `sessions`/
+/// `events` rows into a fresh database file and swaps it in atomically,
+/// keeping the damaged file as a `.corrupt` backup. Gated by the same shared
+/// secret as the other admin endpoints so it can be triggered on demand
+/// without restarting the server.
+pub async fn run_integrity_check(req: HttpRequest, data: web::Data<AppState>) -> Result<HttpResponse, Error> {
+    // Check for shared secret
+    if !check_secret_key(&req, &data.config.secret_key) {
+        return Ok(HttpResponse::Unauthorized().body("Invalid secret key"));
+    }
+
+    let report = db_pool::check_and_recover_integrity();
+
+    Ok(HttpResponse::Ok().json(IntegrityCheckResponse {
+        was_healthy: report.was_healthy,
+        recovered_rows: report.recovered_rows,
+        dropped_rows: report.dropped_rows,
+        detail: report.detail,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rusqlite::Connection;
+
+    #[test]
+    fn push_json_predicate_casts_numbers_through_real() {
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        push_json_predicate(&mut clauses, &mut bound, "$.level", ">=", &Value::from(5)).unwrap();
+
+        assert_eq!(clauses, vec!["CAST(json_extract(params, '$.level') AS REAL) >= ?".to_string()]);
+        assert_eq!(bound.len(), 1);
+    }
+
+    #[test]
+    fn push_json_predicate_binds_strings_without_cast() {
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        push_json_predicate(&mut clauses, &mut bound, "$.biome", "=", &Value::from("caves")).unwrap();
+
+        assert_eq!(clauses, vec!["json_extract(params, '$.biome') = ?".to_string()]);
+        assert_eq!(bound.len(), 1);
+    }
+
+    #[test]
+    fn push_json_predicate_binds_bools_as_integers() {
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        push_json_predicate(&mut clauses, &mut bound, "$.done", "=", &Value::from(true)).unwrap();
+
+        assert_eq!(clauses, vec!["json_extract(params, '$.done') = ?".to_string()]);
+        assert_eq!(bound.len(), 1);
+        assert!(matches!(
+            rusqlite::ToSql::to_sql(bound[0].as_ref()).unwrap(),
+            rusqlite::types::ToSqlOutput::Owned(rusqlite::types::Value::Integer(1))
+        ));
+    }
+
+    #[test]
+    fn push_json_predicate_rejects_unsupported_value_types() {
+        let mut clauses = Vec::new();
+        let mut bound: Vec<Box<dyn rusqlite::ToSql>> = Vec::new();
+
+        let result = push_json_predicate(&mut clauses, &mut bound, "$.tags", "=", &Value::Array(vec![]));
+
+        assert!(result.is_err());
+        assert!(clauses.is_empty());
+    }
+
+    fn events_table_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                session_id TEXT NOT NULL,
+                event_name TEXT NOT NULL,
+                time INTEGER NOT NULL,
+                ip_address TEXT NOT NULL,
+                params TEXT NOT NULL,
+                UNIQUE(session_id, event_name, time)
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    fn batch_item(event_name: &str, time: u64) -> BatchEventItem {
+        BatchEventItem {
+            event_name: event_name.to_string(),
+            time,
+            params: serde_json::json!({}),
+        }
+    }
+
+    #[test]
+    fn insert_batch_accepts_every_well_formed_event() {
+        let mut conn = events_table_db();
+        let tx = conn.transaction().unwrap();
+        let events = vec![batch_item("level_up", 1), batch_item("death", 2)];
+
+        let (inserted, rejected) = insert_batch(&tx, "s1", "127.0.0.1", &events);
+
+        assert_eq!(inserted.len(), 2);
+        assert!(rejected.is_empty());
+    }
+
+    #[test]
+    fn insert_batch_reports_rejections_by_index_without_aborting_the_rest() {
+        let mut conn = events_table_db();
+        let tx = conn.transaction().unwrap();
+        // Same (session_id, event_name, time) as the first row, so it collides
+        // with the UNIQUE constraint and is rejected on its own.
+        let events = vec![batch_item("level_up", 1), batch_item("level_up", 1), batch_item("death", 2)];
+
+        let (inserted, rejected) = insert_batch(&tx, "s1", "127.0.0.1", &events);
+
+        assert_eq!(inserted.len(), 2);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].index, 1);
+    }
+
+    #[test]
+    fn check_ingest_token_rejects_a_request_missing_the_header() {
+        let req = actix_web::test::TestRequest::default().to_http_request();
+
+        let result = check_ingest_token(&req, "s1");
+
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,284 @@
+use std::time::{Duration, Instant};
+
+use actix::{Actor, ActorContext, Addr, AsyncContext, Handler, Message, StreamHandler};
+use actix_web::{web, Error, HttpRequest, HttpResponse};
+use actix_web_actors::ws;
+use serde::Deserialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::app_state::AppState;
+use crate::db_pool;
+use crate::route_handlers::{check_secret_key, Event, EventNameFilter};
+
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(5);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+struct SubscribeFrame {
+    session_id: String,
+    #[serde(default)]
+    since: u64,
+    #[serde(default)]
+    event_name: Option<EventNameFilter>,
+}
+
+#[derive(Message, Clone)]
+#[rtype(result = "()")]
+struct Broadcast(Event);
+
+/// Sent when this subscriber's broadcast receiver lagged (it couldn't keep
+/// up with the publish rate) so the connection is dropped rather than
+/// silently skipping ahead and pretending nothing was missed.
+#[derive(Message)]
+#[rtype(result = "()")]
+struct Disconnect;
+
+pub async fn subscribe(
+    req: HttpRequest,
+    stream: web::Payload,
+    data: web::Data<AppState>,
+) -> Result<HttpResponse, Error> {
+    if !check_secret_key(&req, &data.config.secret_key) {
+        return Ok(HttpResponse::Unauthorized().body("Invalid secret key"));
+    }
+
+    ws::start(EventSubscription::new(data), &req, stream)
+}
+
+struct EventSubscription {
+    hb: Instant,
+    data: web::Data<AppState>,
+    session_id: Option<String>,
+    event_name: Option<EventNameFilter>,
+}
+
+impl EventSubscription {
+    fn new(data: web::Data<AppState>) -> Self {
+        Self {
+            hb: Instant::now(),
+            data,
+            session_id: None,
+            event_name: None,
+        }
+    }
+
+    /// Disconnect a peer that has stopped answering pings so one stalled client
+    /// can't pin an ingest-side broadcast receiver open forever.
+    fn heartbeat(&self, ctx: &mut ws::WebsocketContext<Self>) {
+        ctx.run_interval(HEARTBEAT_INTERVAL, |act, ctx| {
+            if Instant::now().duration_since(act.hb) > CLIENT_TIMEOUT {
+                ctx.stop();
+                return;
+            }
+            ctx.ping(b"");
+        });
+    }
+
+    fn replay_backlog(
+        &self,
+        session_id: &str,
+        since: u64,
+        event_name: Option<&EventNameFilter>,
+        ctx: &mut ws::WebsocketContext<Self>,
+    ) {
+        let session_id = session_id.to_string();
+        let events = db_pool::with_connection(|conn| {
+            let mut sql = "SELECT id, session_id, event_name, time, params FROM events \
+                 WHERE session_id = ?1 AND time >= ?2"
+                .to_string();
+            let mut bound: Vec<Box<dyn rusqlite::ToSql>> = vec![Box::new(session_id.clone()), Box::new(since)];
+
+            match event_name {
+                Some(EventNameFilter::One(name)) => {
+                    sql.push_str(" AND event_name = ?");
+                    bound.push(Box::new(name.clone()));
+                }
+                Some(EventNameFilter::AnyOf(names)) if !names.is_empty() => {
+                    let placeholders = names.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
+                    sql.push_str(&format!(" AND event_name IN ({placeholders})"));
+                    for name in names {
+                        bound.push(Box::new(name.clone()));
+                    }
+                }
+                _ => {}
+            }
+            sql.push_str(" ORDER BY time ASC");
+
+            let mut stmt = conn.prepare_cached(&sql).unwrap();
+            let bound_refs: Vec<&dyn rusqlite::ToSql> = bound.iter().map(|b| b.as_ref()).collect();
+
+            stmt.query_map(bound_refs.as_slice(), |row| {
+                let params_str: String = row.get(4)?;
+                Ok(Event {
+                    id: row.get(0)?,
+                    session_id: row.get(1)?,
+                    event_name: row.get(2)?,
+                    time: row.get(3)?,
+                    params: serde_json::from_str(&params_str).unwrap_or(serde_json::Value::Null),
+                })
+            })
+            .unwrap()
+            .map(|event| event.unwrap())
+            .collect::<Vec<Event>>()
+        });
+
+        for event in events {
+            ctx.text(serde_json::to_string(&event).unwrap());
+        }
+    }
+
+    /// Bridge the shared `broadcast` channel (bounded, lossy by design) into this
+    /// actor's mailbox so `ingest_event`'s publish never blocks on a subscriber;
+    /// if this receiver lags, the connection is dropped (see `Disconnect`). The
+    /// task ends, releasing its receiver, once the actor has stopped.
+    fn forward_live_events(&self, addr: Addr<Self>) {
+        let mut rx = self.data.event_tx.subscribe();
+        actix::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(_) if !addr.connected() => break,
+                    Ok(event) => addr.do_send(Broadcast(event)),
+                    Err(RecvError::Lagged(_)) => {
+                        addr.do_send(Disconnect);
+                        break;
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+impl Actor for EventSubscription {
+    type Context = ws::WebsocketContext<Self>;
+
+    fn started(&mut self, ctx: &mut Self::Context) {
+        self.heartbeat(ctx);
+        self.forward_live_events(ctx.address());
+    }
+}
+
+/// Whether a freshly published `event` should be forwarded to a subscriber
+/// that picked `session_id`/`event_name` as its filter. `session_id: None`
+/// (not yet subscribed) never matches.
+fn event_matches(session_id: &Option<String>, event_name: &Option<EventNameFilter>, event: &Event) -> bool {
+    let session_matches = session_id.as_deref() == Some(event.session_id.as_str());
+    let name_matches = event_name.as_ref().is_none_or(|filter| filter.matches(&event.event_name));
+    session_matches && name_matches
+}
+
+impl Handler<Broadcast> for EventSubscription {
+    type Result = ();
+
+    fn handle(&mut self, msg: Broadcast, ctx: &mut Self::Context) {
+        if event_matches(&self.session_id, &self.event_name, &msg.0) {
+            ctx.text(serde_json::to_string(&msg.0).unwrap());
+        }
+    }
+}
+
+impl Handler<Disconnect> for EventSubscription {
+    type Result = ();
+
+    fn handle(&mut self, _msg: Disconnect, ctx: &mut Self::Context) {
+        ctx.stop();
+    }
+}
+
+impl StreamHandler<Result<ws::Message, ws::ProtocolError>> for EventSubscription {
+    fn handle(&mut self, msg: Result<ws::Message, ws::ProtocolError>, ctx: &mut Self::Context) {
+        match msg {
+            Ok(ws::Message::Ping(msg)) => {
+                self.hb = Instant::now();
+                ctx.pong(&msg);
+            }
+            Ok(ws::Message::Pong(_)) => {
+                self.hb = Instant::now();
+            }
+            Ok(ws::Message::Text(text)) => {
+                if self.session_id.is_some() {
+                    // Already subscribed; a session only gets to pick its filter once.
+                    return;
+                }
+                let frame: SubscribeFrame = match serde_json::from_str(&text) {
+                    Ok(frame) => frame,
+                    Err(_) => {
+                        ctx.text(r#"{"error":"invalid subscribe frame"}"#);
+                        return;
+                    }
+                };
+                self.replay_backlog(&frame.session_id, frame.since, frame.event_name.as_ref(), ctx);
+                self.session_id = Some(frame.session_id);
+                self.event_name = frame.event_name;
+            }
+            Ok(ws::Message::Close(reason)) => {
+                ctx.close(reason);
+                ctx.stop();
+            }
+            Ok(ws::Message::Binary(_)) | Ok(ws::Message::Continuation(_)) | Ok(ws::Message::Nop) => {}
+            Err(_) => ctx.stop(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(session_id: &str, event_name: &str) -> Event {
+        Event {
+            id: 1,
+            session_id: session_id.to_string(),
+            event_name: event_name.to_string(),
+            time: 0,
+            params: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn event_matches_requires_the_subscribed_session_id() {
+        let session_id = Some("s1".to_string());
+
+        assert!(!event_matches(&session_id, &None, &event("s2", "level_up")));
+        assert!(event_matches(&session_id, &None, &event("s1", "level_up")));
+    }
+
+    #[test]
+    fn event_matches_never_matches_before_a_session_is_chosen() {
+        assert!(!event_matches(&None, &None, &event("s1", "level_up")));
+    }
+
+    #[test]
+    fn event_matches_applies_the_event_name_filter() {
+        let session_id = Some("s1".to_string());
+        let filter = Some(EventNameFilter::One("level_up".to_string()));
+
+        assert!(event_matches(&session_id, &filter, &event("s1", "level_up")));
+        assert!(!event_matches(&session_id, &filter, &event("s1", "death")));
+    }
+
+    #[test]
+    fn event_matches_applies_an_any_of_event_name_filter() {
+        let session_id = Some("s1".to_string());
+        let filter = Some(EventNameFilter::AnyOf(vec!["level_up".to_string(), "death".to_string()]));
+
+        assert!(event_matches(&session_id, &filter, &event("s1", "death")));
+        assert!(!event_matches(&session_id, &filter, &event("s1", "chest_opened")));
+    }
+
+    #[test]
+    fn subscribe_frame_parses_an_any_of_event_name_filter() {
+        let frame: SubscribeFrame =
+            serde_json::from_str(r#"{"session_id":"s1","event_name":["level_up","death"]}"#).unwrap();
+
+        assert_eq!(frame.session_id, "s1");
+        assert!(matches!(frame.event_name, Some(EventNameFilter::AnyOf(names)) if names == ["level_up", "death"]));
+    }
+
+    #[test]
+    fn subscribe_frame_defaults_event_name_to_unfiltered() {
+        let frame: SubscribeFrame = serde_json::from_str(r#"{"session_id":"s1"}"#).unwrap();
+
+        assert!(frame.event_name.is_none());
+    }
+}